@@ -0,0 +1,167 @@
+use num_complex::Complex;
+use rayon::prelude::*;
+
+use crate::FractalType;
+
+pub(crate) enum Device {
+    Cpu,
+    Gpu
+}
+
+impl Device {
+    pub(crate) fn parse(s: &str) -> Option<Device> {
+        match s {
+            "cpu" => Some(Device::Cpu),
+            "gpu" => Some(Device::Gpu),
+            _ => None
+        }
+    }
+}
+
+pub(crate) struct EscapeResult {
+    pub(crate) iterations: u32,
+    pub(crate) norm: f64
+}
+
+pub(crate) trait ComputeBackend {
+    fn escape_one(&self, z0: Complex<f64>, c: Complex<f64>, max_iter: u32, bailout: f64, fractal_type: FractalType) -> EscapeResult {
+        let mut i = 0;
+        let mut w = z0;
+        while i < max_iter && w.norm() <= bailout {
+            w = match fractal_type {
+                FractalType::BurningShip => {
+                    let w = Complex::new(w.re.abs(), w.im.abs());
+                    w * w + c
+                },
+                FractalType::Multibrot(degree) => w.powu(degree as u32) + c,
+                FractalType::Mandelbrot | FractalType::Julia(_) => w * w + c
+            };
+            i += 1;
+        }
+        EscapeResult { iterations: i, norm: w.norm() }
+    }
+
+    fn escape_batch(&self, z0: Complex<f64>, points: &[Complex<f64>], max_iter: u32, bailout: f64, fractal_type: FractalType) -> Vec<EscapeResult> {
+        points.iter().map(|&c| self.escape_one(z0, c, max_iter, bailout, fractal_type)).collect()
+    }
+}
+
+pub(crate) struct CpuBackend;
+
+impl ComputeBackend for CpuBackend {
+    fn escape_batch(&self, z0: Complex<f64>, points: &[Complex<f64>], max_iter: u32, bailout: f64, fractal_type: FractalType) -> Vec<EscapeResult> {
+        points.par_iter().map(|&c| self.escape_one(z0, c, max_iter, bailout, fractal_type)).collect()
+    }
+}
+
+// Runs the quadratic update on the GPU via a wgpu compute shader. The
+// shader only implements `w = w*w + c`, so escape_batch falls back to
+// CpuBackend for fractal types it can't express (Burning Ship, Multibrot).
+pub(crate) struct GpuBackend {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    pipeline: wgpu::ComputePipeline
+}
+
+impl GpuBackend {
+    pub(crate) fn try_new() -> Option<GpuBackend> {
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::default());
+        let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions::default()))?;
+        let (device, queue) = pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor::default(), None)).ok()?;
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("escape"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("escape.wgsl").into())
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("escape_pipeline"),
+            layout: None,
+            module: &shader,
+            entry_point: "main"
+        });
+        Some(GpuBackend { device, queue, pipeline })
+    }
+
+    fn dispatch(&self, z0: Complex<f64>, points: &[Complex<f64>], max_iter: u32, bailout: f64) -> Vec<EscapeResult> {
+        use wgpu::util::DeviceExt;
+
+        #[repr(C)]
+        #[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+        struct Params {
+            z0_re: f32,
+            z0_im: f32,
+            max_iter: u32,
+            bailout: f32
+        }
+        #[repr(C)]
+        #[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+        struct Point {
+            re: f32,
+            im: f32
+        }
+
+        let params = Params { z0_re: z0.re as f32, z0_im: z0.im as f32, max_iter, bailout: bailout as f32 };
+        let c_in: Vec<Point> = points.iter().map(|c| Point { re: c.re as f32, im: c.im as f32 }).collect();
+
+        let params_buf = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("params"),
+            contents: bytemuck::bytes_of(&params),
+            usage: wgpu::BufferUsages::UNIFORM
+        });
+        let c_buf = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("points"),
+            contents: bytemuck::cast_slice(&c_in),
+            usage: wgpu::BufferUsages::STORAGE
+        });
+        let out_buf = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("escape_out"),
+            size: (points.len() * std::mem::size_of::<[u32; 2]>()) as u64,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false
+        });
+        let staging_buf = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("escape_staging"),
+            size: out_buf.size(),
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false
+        });
+
+        let bind_group_layout = self.pipeline.get_bind_group_layout(0);
+        let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("escape_bind_group"),
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: params_buf.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 1, resource: c_buf.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 2, resource: out_buf.as_entire_binding() }
+            ]
+        });
+
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("escape_encoder") });
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label: Some("escape_pass"), timestamp_writes: None });
+            pass.set_pipeline(&self.pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.dispatch_workgroups((points.len() as u32).div_ceil(64), 1, 1);
+        }
+        encoder.copy_buffer_to_buffer(&out_buf, 0, &staging_buf, 0, out_buf.size());
+        self.queue.submit(Some(encoder.finish()));
+
+        let slice = staging_buf.slice(..);
+        let (tx, rx) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |res| { let _ = tx.send(res); });
+        self.device.poll(wgpu::Maintain::Wait);
+        rx.recv().expect("gpu staging buffer map channel closed").expect("failed to map gpu staging buffer");
+        let data = slice.get_mapped_range();
+        let raw: &[[u32; 2]] = bytemuck::cast_slice(&data);
+        raw.iter().map(|&[iterations, norm_bits]| EscapeResult { iterations, norm: f32::from_bits(norm_bits) as f64 }).collect()
+    }
+}
+
+impl ComputeBackend for GpuBackend {
+    fn escape_batch(&self, z0: Complex<f64>, points: &[Complex<f64>], max_iter: u32, bailout: f64, fractal_type: FractalType) -> Vec<EscapeResult> {
+        if !matches!(fractal_type, FractalType::Mandelbrot | FractalType::Julia(_)) {
+            return CpuBackend.escape_batch(z0, points, max_iter, bailout, fractal_type);
+        }
+        self.dispatch(z0, points, max_iter, bailout)
+    }
+}
@@ -1,84 +1,139 @@
+mod backend;
+mod perturbation;
+
 use std::env;
-use std::fs::File;
-use std::io::Write;
+use std::fs;
 use std::str::FromStr;
 use num_complex::Complex;
 use image::{ImageBuffer, Rgb};
 use indicatif::{ProgressBar, ProgressStyle};
-use rayon::prelude::*;
 
-enum FractalType {
+use backend::{ComputeBackend, CpuBackend, Device, EscapeResult, GpuBackend};
+
+// samples*samples must fit in a u32 (used for Vec::with_capacity sizing and
+// the r_sum/n averaging), so cap well below the 65536 overflow point.
+const MAX_SAMPLES: u32 = 32;
+
+fn clamp_samples(samples: u32) -> u32 {
+    samples.clamp(1, MAX_SAMPLES)
+}
+
+#[derive(Clone, Copy)]
+pub(crate) enum FractalType {
     Mandelbrot,
-    Julia(Complex<f64>)
+    Julia(Complex<f64>),
+    BurningShip,
+    Multibrot(i32)
 }
 
-fn compute_color(z: Complex<f64>, c: Complex<f64>, max_iter: u32, color_scheme: ColorScheme) -> Rgb<u8> {
-    let mut i = 0;
-    let mut w = z;
-    while i < max_iter && w.norm() <= 2.0 {
-        w = w * w + c;
-        i += 1;
-    }
-    let color = match color_scheme {
-        ColorScheme::BlackAndWhite => {
-            if i == max_iter {
-                Rgb([0, 0, 0])
-            } else {
-                let intensity = (i as f64 / max_iter as f64) * 255.0;
+// Rendering knobs shared by every entry point (single image, animation,
+// deep zoom), grouped to keep draw_fractal/render_deep_zoom under clippy's
+// too-many-arguments threshold as new knobs accumulate.
+#[derive(Clone, Copy)]
+pub(crate) struct RenderOptions {
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    pub(crate) max_iter: u32,
+    pub(crate) scale: f64,
+    pub(crate) fractal_type: FractalType,
+    pub(crate) smooth: bool,
+    pub(crate) samples: u32
+}
+
+pub(crate) fn colorize(result: &EscapeResult, max_iter: u32, color_scheme: ColorScheme, smooth: bool) -> Rgb<u8> {
+    let t = if result.iterations == max_iter {
+        None
+    } else if smooth {
+        let mu = result.iterations as f64 + 1.0 - (result.norm.ln().ln() / 2f64.ln());
+        Some(mu / max_iter as f64)
+    } else {
+        Some(result.iterations as f64 / max_iter as f64)
+    };
+    match t {
+        None => Rgb([0, 0, 0]),
+        Some(t) => match color_scheme {
+            ColorScheme::BlackAndWhite | ColorScheme::Grayscale => {
+                let intensity = t * 255.0;
                 Rgb([intensity as u8, intensity as u8, intensity as u8])
-            }
-        },
-        ColorScheme::Rainbow => {
-            if i == max_iter {
-                Rgb([0, 0, 0])
-            } else {
-                let r = (i as f64 / max_iter as f64).powf(0.3);
-                let g = (i as f64 / max_iter as f64).powf(0.5);
-                let b = 1.0 - (i as f64 / max_iter as f64).powf(0.7);
+            },
+            ColorScheme::Rainbow => {
+                let r = t.powf(0.3);
+                let g = t.powf(0.5);
+                let b = 1.0 - t.powf(0.7);
                 Rgb([(r * 255.0) as u8, (g * 255.0) as u8, (b * 255.0) as u8])
             }
-        },
-        ColorScheme::Grayscale => {
-            if i == max_iter {
-                Rgb([0, 0, 0])
-            } else {
-                let intensity = (i as f64 / max_iter as f64) * 255.0;
-                Rgb([intensity as u8, intensity as u8, intensity as u8])
-            }
         }
-    };
-    color
+    }
 }
 
-enum ColorScheme {
+#[derive(Clone, Copy)]
+pub(crate) enum ColorScheme {
     BlackAndWhite,
     Rainbow,
     Grayscale
 }
 
-fn draw_fractal(width: u32, height: u32, max_iter: u32, scale: f64, fractal_type: FractalType, zoom_level: f64, pan_position: (f64, f64)) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
-    let mut imgbuf = ImageBuffer::new(width, height);
+fn select_backend(device: Device) -> Box<dyn ComputeBackend> {
+    match device {
+        Device::Cpu => Box::new(CpuBackend),
+        Device::Gpu => match GpuBackend::try_new() {
+            Some(gpu) => Box::new(gpu),
+            None => {
+                eprintln!("no gpu adapter available, falling back to cpu backend");
+                Box::new(CpuBackend)
+            }
+        }
+    }
+}
+
+fn draw_fractal(opts: &RenderOptions, zoom_level: f64, pan_position: (f64, f64), backend: &dyn ComputeBackend) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+    let RenderOptions { width, height, max_iter, scale, fractal_type, smooth, samples } = *opts;
     let (w, h) = (width as f64, height as f64);
-    let (capture_w, capture_h) = ((width as f64 / zoom_level) as u32, (height as f64 / zoom_level) as u32);
     let (pan_x, pan_y) = pan_position;
-    let (view_w, view_h) = (capture_w as f64 / w * scale, capture_h as f64 / h * scale);
-    let (view_x, view_y) = (pan_x - view_w / 2.0, pan_y - view_h / 2.0);
-    let pb = ProgressBar::new((capture_w * capture_h) as u64);
+    let step_x = scale / w / zoom_level;
+    let step_y = scale / h / zoom_level;
+    let bailout = if smooth { 256.0 } else { 2.0 };
+    let n = samples * samples;
+
+    let mut points = Vec::with_capacity(width as usize * height as usize * n as usize);
+    for y in 0..height {
+        for x in 0..width {
+            for sy in 0..samples {
+                for sx in 0..samples {
+                    let dx = (sx as f64 + 0.5) / samples as f64 - 0.5;
+                    let dy = (sy as f64 + 0.5) / samples as f64 - 0.5;
+                    let cx = (x as f64 - 0.5 * w + dx) * step_x + pan_x;
+                    let cy = (y as f64 - 0.5 * h + dy) * step_y + pan_y;
+                    points.push(Complex::new(cx, cy));
+                }
+            }
+        }
+    }
+    let z0 = match fractal_type {
+        FractalType::Julia(z) => z,
+        _ => Complex::new(0.0, 0.0)
+    };
+
+    let pb = ProgressBar::new(points.len() as u64);
     pb.set_style(ProgressStyle::default_bar()
         .template("[{elapsed_precise}] {bar:40.cyan/blue} {pos}/{len} ({percent}%)")
         .progress_chars("#>-"));
-    imgbuf.enumerate_pixels_mut().par_bridge().for_each(|(x, y, pixel)| {
-        let cx = (x as f64 - 0.5 * capture_w as f64) * scale / w + view_x;
-        let cy = (y as f64 - 0.5 * capture_h as f64) * scale / h + view_y;
-        let c = Complex::new(cx, cy);
-        let color = match fractal_type {
-            FractalType::Mandelbrot => compute_color(Complex::new(0.0, 0.0), c, max_iter),
-            FractalType::Julia(z) => compute_color(z, c, max_iter)
-        };
-        *pixel = color;
-        pb.inc(1);
-    });
+    let results = backend.escape_batch(z0, &points, max_iter, bailout, fractal_type);
+    pb.set_position(results.len() as u64);
     pb.finish_with_message("done");
+
+    let mut imgbuf = ImageBuffer::new(width, height);
+    for (idx, pixel) in imgbuf.pixels_mut().enumerate() {
+        let base = idx * n as usize;
+        let (mut r_sum, mut g_sum, mut b_sum) = (0u32, 0u32, 0u32);
+        for result in &results[base..base + n as usize] {
+            let color = colorize(result, max_iter, ColorScheme::Rainbow, smooth);
+            r_sum += color[0] as u32;
+            g_sum += color[1] as u32;
+            b_sum += color[2] as u32;
+        }
+        *pixel = Rgb([(r_sum / n) as u8, (g_sum / n) as u8, (b_sum / n) as u8]);
+    }
     imgbuf
 }
 
@@ -102,29 +157,123 @@ fn parse_complex_number(s: &str) -> Option<Complex<f64>> {
     Some(Complex::new(re, im))
 }
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let args: Vec<String> = env::args().collect();
-    if args.len() != 8 {
-        println!("Usage: {} <output_file> <width>x<height> <capture_width>x<capture_height> <max_iter> <scale> <fractal_type> <c>", args[0]);
+fn parse_fractal_type(name: &str, c_arg: &str) -> Result<FractalType, Box<dyn std::error::Error>> {
+    Ok(match name {
+        "mandelbrot" => FractalType::Mandelbrot,
+        "julia" => {
+            let c = parse_complex_number(c_arg).ok_or("Invalid complex number")?;
+            FractalType::Julia(c)
+        },
+        "burning_ship" => FractalType::BurningShip,
+        name if name.starts_with("multibrot:") => {
+            let degree = i32::from_str(&name["multibrot:".len()..]).map_err(|_| "Invalid multibrot degree")?;
+            if degree < 2 {
+                return Err("Invalid multibrot degree".into());
+            }
+            FractalType::Multibrot(degree)
+        },
+        _ => return Err("Invalid fractal type".into())
+    })
+}
+
+fn parse_trailing_flags(tail: &[String]) -> Result<(bool, Device), Box<dyn std::error::Error>> {
+    let mut smooth = false;
+    let mut device = Device::Cpu;
+    for flag in tail {
+        if flag == "smooth" {
+            smooth = true;
+        } else if let Some(name) = flag.strip_prefix("device:") {
+            device = Device::parse(name).ok_or("Invalid device")?;
+        } else {
+            return Err(format!("Invalid trailing flag: {}", flag).into());
+        }
+    }
+    Ok((smooth, device))
+}
+
+fn run_single_image(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    if args.len() < 8 || args.len() > 10 {
+        println!("Usage: {} <output_file> <width>x<height> <samples> <max_iter> <scale> <fractal_type> <c> [smooth] [device:cpu|gpu]", args[0]);
         return Ok(());
     }
+    let (smooth, device) = parse_trailing_flags(&args[8..])?;
     let output_file = &args[1];
     let (width, height) = parse_resolution(&args[2]).ok_or("Invalid resolution")?;
-    let (capture_width, capture_height) = parse_resolution(&args[3]).ok_or("Invalid capture size")?;
+    let samples = u32::from_str(&args[3]).map_err(|_| "Invalid sample count")?;
     let max_iter = u32::from_str(&args[4]).map_err(|_| "Invalid max_iter")?;
     let scale = f64::from_str(&args[5]).map_err(|_| "Invalid scale")?;
-    let fractal_type = match &args[6][..] {
-        "mandelbrot" => FractalType::Mandelbrot,
-        "julia" => {
-            let c = parse_complex_number(&args[7]).ok_or("Invalid complex number")?;
-            FractalType::Julia(c)
-        },
-        _ => return Err("Invalid fractal type".into())
+    let fractal_type = parse_fractal_type(&args[6], &args[7])?;
+    let backend = select_backend(device);
+    let opts = RenderOptions { width, height, max_iter, scale, fractal_type, smooth, samples: clamp_samples(samples) };
+    let imgbuf = draw_fractal(&opts, 1.0, (0.0, 0.0), backend.as_ref());
+    imgbuf.save(output_file)?;
+    Ok(())
+}
+
+fn run_animation(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    if args.len() < 13 || args.len() > 15 {
+        println!("Usage: {} animate <output_dir> <width>x<height> <samples> <max_iter> <scale> <fractal_type> <c> <target_cx,target_cy> <start_zoom> <end_zoom> <frames> [smooth] [device:cpu|gpu]", args[0]);
+        return Ok(());
+    }
+    let (smooth, device) = parse_trailing_flags(&args[13..])?;
+    let output_dir = &args[2];
+    let (width, height) = parse_resolution(&args[3]).ok_or("Invalid resolution")?;
+    let samples = u32::from_str(&args[4]).map_err(|_| "Invalid sample count")?;
+    let max_iter = u32::from_str(&args[5]).map_err(|_| "Invalid max_iter")?;
+    let scale = f64::from_str(&args[6]).map_err(|_| "Invalid scale")?;
+    let fractal_type = parse_fractal_type(&args[7], &args[8])?;
+    let target = parse_complex_number(&args[9]).ok_or("Invalid target point")?;
+    let start_zoom = f64::from_str(&args[10]).map_err(|_| "Invalid start zoom")?;
+    let end_zoom = f64::from_str(&args[11]).map_err(|_| "Invalid end zoom")?;
+    let frames = u32::from_str(&args[12]).map_err(|_| "Invalid frame count")?;
+    if frames == 0 {
+        return Err("Frame count must be at least 1".into());
+    }
+    fs::create_dir_all(output_dir)?;
+    let backend = select_backend(device);
+    let opts = RenderOptions { width, height, max_iter, scale, fractal_type, smooth, samples: clamp_samples(samples) };
+    for frame in 0..frames {
+        let t = if frames == 1 { 0.0 } else { frame as f64 / (frames - 1) as f64 };
+        let zoom_level = start_zoom * (end_zoom / start_zoom).powf(t);
+        let imgbuf = draw_fractal(&opts, zoom_level, (target.re, target.im), backend.as_ref());
+        let frame_path = format!("{}/frame_{:05}.png", output_dir, frame);
+        imgbuf.save(&frame_path)?;
+    }
+    Ok(())
+}
+
+fn run_deep_zoom(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    if args.len() != 10 && args.len() != 11 {
+        println!("Usage: {} deepzoom <output_file> <width>x<height> <samples> <max_iter> <scale> <fractal_type> <c> <center_re,center_im> [smooth]", args[0]);
+        return Ok(());
+    }
+    let smooth = match args.get(10) {
+        None => false,
+        Some(flag) if flag == "smooth" => true,
+        Some(_) => return Err("Invalid trailing flag".into())
     };
-    let imgbuf = draw_fractal(capture_width, capture_height, max_iter, scale, fractal_type);
-    let resized = image::imageops::resize(&imgbuf, width, height, image::imageops::FilterType::Lanczos3);
-    let mut file = File::create(output_file)?;
-    resized.save_with_format(&mut file, image::ImageFormat::PNG)?;
+    let output_file = &args[2];
+    let (width, height) = parse_resolution(&args[3]).ok_or("Invalid resolution")?;
+    let samples = u32::from_str(&args[4]).map_err(|_| "Invalid sample count")?;
+    let max_iter = u32::from_str(&args[5]).map_err(|_| "Invalid max_iter")?;
+    let scale = f64::from_str(&args[6]).map_err(|_| "Invalid scale")?;
+    if scale <= 0.0 {
+        return Err("Scale must be positive".into());
+    }
+    let fractal_type = parse_fractal_type(&args[7], &args[8])?;
+    let center = args[9].split_once(',').ok_or("Invalid center point")?;
+    let opts = RenderOptions { width, height, max_iter, scale, fractal_type, smooth, samples: clamp_samples(samples) };
+    let imgbuf = perturbation::render_deep_zoom(&opts, center)?;
+    imgbuf.save(output_file)?;
     Ok(())
 }
 
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args: Vec<String> = env::args().collect();
+    match args.get(1).map(|s| s.as_str()) {
+        Some("animate") => run_animation(&args),
+        Some("deepzoom") => run_deep_zoom(&args),
+        _ => run_single_image(&args)
+    }
+}
+